@@ -0,0 +1,287 @@
+// Runtime configuration for wmouse, loaded from
+// `$XDG_CONFIG_HOME/wmouse/config.toml` (or `~/.config/wmouse/config.toml`
+// when `XDG_CONFIG_HOME` is unset). Falls back to the built-in defaults when
+// no file is present, so an existing install keeps working untouched.
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use xkbcommon::xkb;
+
+// Maps an xkb keysym to the evdev keycode that produces it, so config names
+// like "w" or "F5" resolve without a hand-maintained table. xkb keycodes are
+// evdev keycodes offset by 8 (the X11 legacy that xkbcommon keymaps inherit).
+type KeysymTable = HashMap<u32, u16>;
+
+// Built-in defaults, matching the keys wmouse shipped with before config
+// support existed.
+const DEFAULT_MODIFIER: u16 = 125; // Super/Windows key
+const DEFAULT_MOVE_UP: u16 = 103; // Up arrow
+const DEFAULT_MOVE_DOWN: u16 = 108; // Down arrow
+const DEFAULT_MOVE_LEFT: u16 = 105; // Left arrow
+const DEFAULT_MOVE_RIGHT: u16 = 106; // Right arrow
+const DEFAULT_MOUSE_LEFT: u16 = 97; // Right Control
+const DEFAULT_MOUSE_RIGHT: u16 = 96; // Keypad Enter
+const DEFAULT_TICK_MS: u64 = 8;
+
+// Mouse-keys-style acceleration curve defaults: a slow start for precise
+// positioning, ramping up to a fast top speed for large traversals.
+const DEFAULT_ACCEL_BASE: f64 = 4.0;
+const DEFAULT_ACCEL_MAX: f64 = 30.0;
+const DEFAULT_ACCEL_DELAY_MS: u64 = 150;
+const DEFAULT_ACCEL_RAMP_MS: u64 = 400;
+const DEFAULT_ACCEL_EXPONENT: f64 = 2.0;
+
+// Flat per-tick scroll step; scrolling has no acceleration curve.
+const DEFAULT_SCROLL_SPEED: f64 = 1.0;
+
+#[derive(Deserialize, Default)]
+struct RawConfig {
+    #[serde(default)]
+    keys: RawKeys,
+    #[serde(default)]
+    mouse: RawMouse,
+}
+
+#[derive(Deserialize, Default)]
+struct RawKeys {
+    modifier: Option<Vec<String>>,
+    move_up: Option<Vec<String>>,
+    move_down: Option<Vec<String>>,
+    move_left: Option<Vec<String>>,
+    move_right: Option<Vec<String>>,
+    left_click: Option<Vec<String>>,
+    right_click: Option<Vec<String>>,
+    middle_click: Option<Vec<String>>,
+    scroll_up: Option<Vec<String>>,
+    scroll_down: Option<Vec<String>>,
+    drag_lock: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawMouse {
+    tick_ms: Option<u64>,
+    accel_base: Option<f64>,
+    accel_max: Option<f64>,
+    accel_delay_ms: Option<u64>,
+    accel_ms: Option<u64>,
+    accel_exponent: Option<f64>,
+    scroll_speed: Option<f64>,
+}
+
+// Mouse-keys-style acceleration curve: the step taken on a tick ramps from
+// `base` to `max` over `accel_ms`, after an initial `delay_ms` dead time,
+// following `exponent` (1.0 = linear ramp, >1.0 = slower start).
+pub struct Acceleration {
+    pub base: f64,
+    pub max: f64,
+    pub delay_ms: u64,
+    pub accel_ms: u64,
+    pub exponent: f64,
+}
+
+impl Default for Acceleration {
+    fn default() -> Self {
+        Self {
+            base: DEFAULT_ACCEL_BASE,
+            max: DEFAULT_ACCEL_MAX,
+            delay_ms: DEFAULT_ACCEL_DELAY_MS,
+            accel_ms: DEFAULT_ACCEL_RAMP_MS,
+            exponent: DEFAULT_ACCEL_EXPONENT,
+        }
+    }
+}
+
+// Final, resolved bindings: each action is a set of evdev keycodes that
+// trigger it, so a user can bind more than one physical key to the same
+// action.
+pub struct Bindings {
+    pub modifier: HashSet<u16>,
+    pub move_up: HashSet<u16>,
+    pub move_down: HashSet<u16>,
+    pub move_left: HashSet<u16>,
+    pub move_right: HashSet<u16>,
+    pub left_click: HashSet<u16>,
+    pub right_click: HashSet<u16>,
+    pub middle_click: HashSet<u16>,
+    pub scroll_up: HashSet<u16>,
+    pub scroll_down: HashSet<u16>,
+    pub drag_lock: HashSet<u16>,
+    pub accel: Acceleration,
+    pub scroll_speed: f64,
+    pub tick_ms: u64,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            modifier: HashSet::from([DEFAULT_MODIFIER]),
+            move_up: HashSet::from([DEFAULT_MOVE_UP]),
+            move_down: HashSet::from([DEFAULT_MOVE_DOWN]),
+            move_left: HashSet::from([DEFAULT_MOVE_LEFT]),
+            move_right: HashSet::from([DEFAULT_MOVE_RIGHT]),
+            left_click: HashSet::from([DEFAULT_MOUSE_LEFT]),
+            right_click: HashSet::from([DEFAULT_MOUSE_RIGHT]),
+            middle_click: HashSet::new(),
+            scroll_up: HashSet::new(),
+            scroll_down: HashSet::new(),
+            drag_lock: HashSet::new(),
+            accel: Acceleration::default(),
+            scroll_speed: DEFAULT_SCROLL_SPEED,
+            tick_ms: DEFAULT_TICK_MS,
+        }
+    }
+}
+
+fn config_path() -> PathBuf {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".config"));
+    base.join("wmouse").join("config.toml")
+}
+
+// Compiles the system's default xkb keymap purely to read off its
+// keysym-per-keycode table, so any name `xkb::keysym_from_name` recognizes
+// (not just the handful wmouse ships defaults for) resolves to an evdev
+// code. Built at most once per `load()`, and only when a config actually
+// names a key. Returns `None` (rather than panicking) if the system has no
+// usable default keymap, same as an unresolved key name: warn and let the
+// caller fall back to defaults.
+fn build_keysym_table() -> Option<KeysymTable> {
+    let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+    let keymap = match xkb::Keymap::new_from_names(
+        &context,
+        &xkb::RuleNames::default(),
+        xkb::KEYMAP_COMPILE_NO_FLAGS,
+    ) {
+        Some(keymap) => keymap,
+        None => {
+            eprintln!(
+                "wmouse: failed to compile a default xkb keymap for key name resolution, using default bindings"
+            );
+            return None;
+        }
+    };
+
+    let mut table = KeysymTable::new();
+    let min = keymap.min_keycode().raw();
+    let max = keymap.max_keycode().raw();
+    for raw_code in min..=max {
+        let keycode = xkb::Keycode::new(raw_code);
+        for sym in keymap.key_get_syms_by_level(keycode, 0, 0) {
+            table.entry(sym.raw()).or_insert((raw_code - 8) as u16);
+        }
+    }
+    Some(table)
+}
+
+// Resolves one key identifier to an evdev keycode. Accepts either a raw
+// evdev code ("105") or an xkb keysym/key name ("Left", "Control_R", "w"),
+// looked up in `table`. Names other than raw codes can't resolve without a
+// table.
+fn resolve_key(raw: &str, table: Option<&KeysymTable>) -> Option<u16> {
+    if let Ok(code) = raw.trim().parse::<u16>() {
+        return Some(code);
+    }
+
+    let table = table?;
+
+    let keysym = xkb::keysym_from_name(raw.trim(), xkb::KEYSYM_NO_FLAGS);
+    if keysym == xkb::Keysym::from(xkb::KEY_NoSymbol) {
+        return None;
+    }
+
+    table.get(&keysym.raw()).copied()
+}
+
+// Resolves every name bound to `action`. If any of them fails to resolve,
+// the whole action falls back to its default binding rather than silently
+// ending up with fewer keys (or none) than the user configured.
+fn resolve_set(
+    action: &str,
+    raw: &Option<Vec<String>>,
+    default: &HashSet<u16>,
+    table: Option<&KeysymTable>,
+) -> HashSet<u16> {
+    let Some(names) = raw else {
+        return default.clone();
+    };
+
+    let mut resolved = HashSet::new();
+    for name in names {
+        match resolve_key(name, table) {
+            Some(code) => {
+                resolved.insert(code);
+            }
+            None => {
+                eprintln!(
+                    "wmouse: key '{name}' for '{action}' not recognized, using default binding"
+                );
+                return default.clone();
+            }
+        }
+    }
+    resolved
+}
+
+pub fn load() -> Bindings {
+    let defaults = Bindings::default();
+
+    let raw = match fs::read_to_string(config_path()) {
+        Ok(contents) => match toml::from_str::<RawConfig>(&contents) {
+            Ok(raw) => raw,
+            Err(err) => {
+                eprintln!("wmouse: ignoring invalid config ({err}), using defaults");
+                return defaults;
+            }
+        },
+        Err(_) => return defaults,
+    };
+
+    // Only compile the keymap if the config actually names a key; a config
+    // that just tweaks `mouse.*` shouldn't pay for (or fail on) xkb setup.
+    let any_key_configured = [
+        &raw.keys.modifier,
+        &raw.keys.move_up,
+        &raw.keys.move_down,
+        &raw.keys.move_left,
+        &raw.keys.move_right,
+        &raw.keys.left_click,
+        &raw.keys.right_click,
+        &raw.keys.middle_click,
+        &raw.keys.scroll_up,
+        &raw.keys.scroll_down,
+        &raw.keys.drag_lock,
+    ]
+    .into_iter()
+    .any(Option::is_some);
+
+    let table = if any_key_configured { build_keysym_table() } else { None };
+    let table = table.as_ref();
+
+    Bindings {
+        modifier: resolve_set("modifier", &raw.keys.modifier, &defaults.modifier, table),
+        move_up: resolve_set("move_up", &raw.keys.move_up, &defaults.move_up, table),
+        move_down: resolve_set("move_down", &raw.keys.move_down, &defaults.move_down, table),
+        move_left: resolve_set("move_left", &raw.keys.move_left, &defaults.move_left, table),
+        move_right: resolve_set("move_right", &raw.keys.move_right, &defaults.move_right, table),
+        left_click: resolve_set("left_click", &raw.keys.left_click, &defaults.left_click, table),
+        right_click: resolve_set("right_click", &raw.keys.right_click, &defaults.right_click, table),
+        middle_click: resolve_set("middle_click", &raw.keys.middle_click, &defaults.middle_click, table),
+        scroll_up: resolve_set("scroll_up", &raw.keys.scroll_up, &defaults.scroll_up, table),
+        scroll_down: resolve_set("scroll_down", &raw.keys.scroll_down, &defaults.scroll_down, table),
+        drag_lock: resolve_set("drag_lock", &raw.keys.drag_lock, &defaults.drag_lock, table),
+        accel: Acceleration {
+            base: raw.mouse.accel_base.unwrap_or(defaults.accel.base),
+            max: raw.mouse.accel_max.unwrap_or(defaults.accel.max),
+            delay_ms: raw.mouse.accel_delay_ms.unwrap_or(defaults.accel.delay_ms),
+            accel_ms: raw.mouse.accel_ms.unwrap_or(defaults.accel.accel_ms),
+            exponent: raw.mouse.accel_exponent.unwrap_or(defaults.accel.exponent),
+        },
+        scroll_speed: raw.mouse.scroll_speed.unwrap_or(defaults.scroll_speed),
+        tick_ms: raw.mouse.tick_ms.unwrap_or(defaults.tick_ms),
+    }
+}
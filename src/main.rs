@@ -1,5 +1,5 @@
 use wayland_client::{
-    protocol::{wl_pointer, wl_seat, wl_registry},
+    protocol::{wl_output, wl_pointer, wl_seat, wl_registry},
     Connection, Dispatch, QueueHandle,
 };
 use wayland_protocols_wlr::virtual_pointer::v1::client::{
@@ -13,27 +13,23 @@ use input::{
     },
     Libinput, LibinputInterface
 };
+use calloop::{
+    generic::Generic,
+    timer::{TimeoutAction, Timer},
+    EventLoop, Interest, Mode, PostAction,
+};
+use calloop_wayland_source::WaylandSource;
 use std::{
-    collections::HashSet,
+    cell::Cell,
+    collections::{HashMap, HashSet},
     fs::OpenOptions,
     os::unix::prelude::*,
     path::Path,
-    thread,
+    rc::Rc,
     time::Duration,
 };
 
-// Key mappings
-const META_KEY: u16 = 125;        // Usually Windows/Super key
-const MOVE_LEFT: u16 = 105;       // Left arrow
-const MOVE_RIGHT: u16 = 106;      // Right arrow
-const MOVE_UP: u16 = 103;         // Up arrow
-const MOVE_DOWN: u16 = 108;       // Down arrow
-const MOUSE_LEFT: u16 = 97;       // Right Control
-const MOUSE_RIGHT: u16 = 96;      // Right Shift
-
-// Mouse settings
-const MOUSE_SPEED: f64 = 10.0;
-const SLEEP_MS: u64 = 8;
+mod config;
 
 struct InputHandler;
 
@@ -52,14 +48,47 @@ impl LibinputInterface for InputHandler {
     }
 }
 
+// Tracks the geometry of a single wl_output so we can compute the combined
+// desktop bounding box once all outputs have reported.
+#[derive(Default, Clone, Copy)]
+struct Output {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+// Whether pointer movement is emitted as an absolute position (clamped to the
+// desktop bounding box) or as relative deltas, selected once at startup.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PointerMode {
+    Absolute,
+    Relative,
+}
+
+// Accumulates the net effect of every active key since the last flush, so a
+// tick that changes several things still produces at most one `motion`, one
+// `button` per changed button and a single `frame()`, as Wayland expects for
+// related pointer events.
 #[derive(Default)]
-struct MouseState {
+struct PendingPointer {
     dx: f64,
     dy: f64,
-    left_click: bool,
-    right_click: bool,
-    x: f64,
-    y: f64,
+    scroll: f64,
+    left_click: Option<bool>,
+    right_click: Option<bool>,
+    middle_click: Option<bool>,
+}
+
+impl PendingPointer {
+    fn is_empty(&self) -> bool {
+        self.dx == 0.0
+            && self.dy == 0.0
+            && self.scroll == 0.0
+            && self.left_click.is_none()
+            && self.right_click.is_none()
+            && self.middle_click.is_none()
+    }
 }
 
 struct State {
@@ -68,81 +97,258 @@ struct State {
     active_keys: HashSet<u16>,
     prev_left_click: bool,
     prev_right_click: bool,
+    prev_middle_click: bool,
+    drag_lock: bool,
+    prev_drag_lock_key: bool,
+    held_up: u32,
+    held_down: u32,
+    held_left: u32,
+    held_right: u32,
+    outputs: HashMap<u32, Output>,
+    pointer_mode: PointerMode,
+    bindings: config::Bindings,
+    pending: PendingPointer,
+    x: f64,
+    y: f64,
 }
 
 impl State {
-    fn new() -> Self {
+    fn new(pointer_mode: PointerMode, bindings: config::Bindings) -> Self {
         Self {
             pointer_manager: None,
             virtual_pointer: None,
             active_keys: HashSet::new(),
             prev_left_click: false,
             prev_right_click: false,
+            prev_middle_click: false,
+            drag_lock: false,
+            prev_drag_lock_key: false,
+            held_up: 0,
+            held_down: 0,
+            held_left: 0,
+            held_right: 0,
+            outputs: HashMap::new(),
+            pointer_mode,
+            bindings,
+            pending: PendingPointer::default(),
+            x: 0.0,
+            y: 0.0,
+        }
+    }
+
+    // Whether any key bound to `action` is currently held down.
+    fn action_active(&self, action: &HashSet<u16>) -> bool {
+        !self.active_keys.is_disjoint(action)
+    }
+
+    // Step size for a direction that has been held for `held_ticks` ticks,
+    // following the configured acceleration curve.
+    fn accel_step(&self, held_ticks: u32) -> f64 {
+        let accel = &self.bindings.accel;
+        let held_ms = held_ticks as u64 * self.bindings.tick_ms;
+        let ramp = held_ms.saturating_sub(accel.delay_ms) as f64 / accel.accel_ms.max(1) as f64;
+        let ramp = ramp.min(1.0).max(0.0).powf(accel.exponent);
+        accel.base + (accel.max - accel.base) * ramp
+    }
+
+    // Whether any movement key is held, used to decide if the tick timer
+    // should keep firing.
+    fn has_movement_keys(&self) -> bool {
+        self.action_active(&self.bindings.move_up)
+            || self.action_active(&self.bindings.move_down)
+            || self.action_active(&self.bindings.move_left)
+            || self.action_active(&self.bindings.move_right)
+    }
+
+    fn has_scroll_keys(&self) -> bool {
+        self.action_active(&self.bindings.scroll_up) || self.action_active(&self.bindings.scroll_down)
+    }
+
+    // Whether the tick timer has anything to keep doing: moving the pointer
+    // or scrolling.
+    fn has_continuous_keys(&self) -> bool {
+        self.has_movement_keys() || self.has_scroll_keys()
+    }
+
+    // Combined bounding box of every known output, as (min_x, min_y, width, height).
+    // Falls back to a single 1920x1080 desktop until at least one output has
+    // reported real geometry (a freshly bound output starts at 0x0 until its
+    // `Geometry`/`Mode` events arrive, and must be excluded until then to
+    // avoid dividing by a zero width/height).
+    fn screen_bounds(&self) -> (f64, f64, f64, f64) {
+        let ready = || self.outputs.values().filter(|o| o.width > 0 && o.height > 0);
+
+        if ready().next().is_none() {
+            return (0.0, 0.0, 1920.0, 1080.0);
         }
+
+        let min_x = ready().map(|o| o.x).min().unwrap();
+        let min_y = ready().map(|o| o.y).min().unwrap();
+        let max_x = ready().map(|o| o.x + o.width).max().unwrap();
+        let max_y = ready().map(|o| o.y + o.height).max().unwrap();
+
+        (min_x as f64, min_y as f64, (max_x - min_x) as f64, (max_y - min_y) as f64)
     }
 
-    fn update_and_handle_mouse_state(&mut self) -> MouseState {
-        let mut state = MouseState::default();
+    // Folds the currently active keys into `self.pending`, ready for the next `flush()`.
+    // Reacts to button and drag-lock key edges. Safe to call on every
+    // libinput event: it only records transitions, so calling it more often
+    // than once per tick doesn't change the outcome.
+    fn update_buttons(&mut self) {
+        if !self.action_active(&self.bindings.modifier) {
+            return;
+        }
 
-        if !self.active_keys.contains(&META_KEY) {
-            return state;
+        // The drag lock key toggles on its press edge, letting the user
+        // start a drag without holding the click key for the whole motion.
+        let drag_lock_key = self.action_active(&self.bindings.drag_lock);
+        if drag_lock_key && !self.prev_drag_lock_key {
+            self.drag_lock = !self.drag_lock;
         }
+        self.prev_drag_lock_key = drag_lock_key;
 
-        // Update movement
-        if self.active_keys.contains(&MOVE_LEFT) { state.dx -= MOUSE_SPEED; }
-        if self.active_keys.contains(&MOVE_RIGHT) { state.dx += MOUSE_SPEED; }
-        if self.active_keys.contains(&MOVE_UP) { state.dy -= MOUSE_SPEED; }
-        if self.active_keys.contains(&MOVE_DOWN) { state.dy += MOUSE_SPEED; }
-
-        // Update absolute position
-        state.x += state.dx;
-        state.y += state.dy;
-
-        // Clamp coordinates to screen bounds (assuming 1920x1080)
-        state.x = state.x.max(0.0).min(1920.0);
-        state.y = state.y.max(0.0).min(1080.0);
-
-        // Update button states
-        state.left_click = self.active_keys.contains(&MOUSE_LEFT);
-        state.right_click = self.active_keys.contains(&MOUSE_RIGHT);
-
-        // Handle the movement
-        if let Some(virtual_pointer) = &self.virtual_pointer {
-            if state.dx != 0.0 || state.dy != 0.0 {
-                virtual_pointer.motion_absolute(
-                    0,  // time
-                    (state.x * 65535.0 / 1920.0) as u32,  // x normalized to 0-65535
-                    (state.y * 65535.0 / 1080.0) as u32,  // y normalized to 0-65535
-                    65535,  // width denominator
-                    65535,  // height denominator
-                );
-                virtual_pointer.frame();
-            }
+        // Record button transitions
+        let left_click = self.action_active(&self.bindings.left_click) || self.drag_lock;
+        if left_click != self.prev_left_click {
+            self.pending.left_click = Some(left_click);
         }
 
-        // Handle button state changes
-        if let Some(virtual_pointer) = &self.virtual_pointer {
-            const BTN_LEFT: u32 = 0x110;   // Standard Linux button codes
-            const BTN_RIGHT: u32 = 0x111;
-
-            // Left click changed
-            if state.left_click != self.prev_left_click {
-                let state_val = if state.left_click { wl_pointer::ButtonState::Pressed } else { wl_pointer::ButtonState::Released };
-                virtual_pointer.button(0, BTN_LEFT, state_val);
-                virtual_pointer.frame();
-                self.prev_left_click = state.left_click;
-            }
+        let right_click = self.action_active(&self.bindings.right_click);
+        if right_click != self.prev_right_click {
+            self.pending.right_click = Some(right_click);
+        }
+
+        let middle_click = self.action_active(&self.bindings.middle_click);
+        if middle_click != self.prev_middle_click {
+            self.pending.middle_click = Some(middle_click);
+        }
+    }
+
+    // Advances the acceleration curve by one tick and accumulates the
+    // resulting movement. Must only be called from the tick timer: the
+    // curve assumes `held_ticks * tick_ms` approximates elapsed time, which
+    // only holds if this runs exactly once per tick.
+    fn tick_movement(&mut self) {
+        if !self.action_active(&self.bindings.modifier) {
+            return;
+        }
+
+        // Accumulate movement, accelerating the step size the longer each
+        // direction has been held.
+        let mut dx = 0.0;
+        let mut dy = 0.0;
+
+        if self.action_active(&self.bindings.move_left) {
+            self.held_left += 1;
+            dx -= self.accel_step(self.held_left);
+        } else {
+            self.held_left = 0;
+        }
+        if self.action_active(&self.bindings.move_right) {
+            self.held_right += 1;
+            dx += self.accel_step(self.held_right);
+        } else {
+            self.held_right = 0;
+        }
+        if self.action_active(&self.bindings.move_up) {
+            self.held_up += 1;
+            dy -= self.accel_step(self.held_up);
+        } else {
+            self.held_up = 0;
+        }
+        if self.action_active(&self.bindings.move_down) {
+            self.held_down += 1;
+            dy += self.accel_step(self.held_down);
+        } else {
+            self.held_down = 0;
+        }
 
-            // Right click changed
-            if state.right_click != self.prev_right_click {
-                let state_val = if state.right_click { wl_pointer::ButtonState::Pressed } else { wl_pointer::ButtonState::Released };
-                virtual_pointer.button(0, BTN_RIGHT, state_val);
-                virtual_pointer.frame();
-                self.prev_right_click = state.right_click;
+        // Normalize diagonal movement so it isn't sqrt(2) faster than a
+        // single axis-aligned direction at the same acceleration stage.
+        if dx != 0.0 && dy != 0.0 {
+            let magnitude = dx.hypot(dy);
+            let target = dx.abs().max(dy.abs());
+            let scale = target / magnitude;
+            dx *= scale;
+            dy *= scale;
+        }
+
+        self.pending.dx += dx;
+        self.pending.dy += dy;
+
+        // Scroll has no acceleration curve: a flat step per tick for as long
+        // as the key is held.
+        let scroll_step = self.bindings.scroll_speed;
+        if self.action_active(&self.bindings.scroll_up) { self.pending.scroll -= scroll_step; }
+        if self.action_active(&self.bindings.scroll_down) { self.pending.scroll += scroll_step; }
+    }
+
+    // Sends at most one motion request, the minimal set of button requests,
+    // and a single `frame()` to commit them atomically.
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let Some(virtual_pointer) = &self.virtual_pointer else {
+            self.pending = PendingPointer::default();
+            return;
+        };
+
+        if self.pending.dx != 0.0 || self.pending.dy != 0.0 {
+            match self.pointer_mode {
+                PointerMode::Absolute => {
+                    // Persist the position across ticks and clamp to the
+                    // combined bounding box of all known outputs.
+                    self.x += self.pending.dx;
+                    self.y += self.pending.dy;
+
+                    let (min_x, min_y, width, height) = self.screen_bounds();
+                    self.x = self.x.max(min_x).min(min_x + width);
+                    self.y = self.y.max(min_y).min(min_y + height);
+
+                    virtual_pointer.motion_absolute(
+                        0,  // time
+                        ((self.x - min_x) * 65535.0 / width) as u32,  // x normalized to 0-65535
+                        ((self.y - min_y) * 65535.0 / height) as u32,  // y normalized to 0-65535
+                        65535,  // width denominator
+                        65535,  // height denominator
+                    );
+                }
+                PointerMode::Relative => {
+                    virtual_pointer.motion(0, self.pending.dx, self.pending.dy);
+                }
             }
         }
 
-        state
+        const BTN_LEFT: u32 = 0x110;   // Standard Linux button codes
+        const BTN_RIGHT: u32 = 0x111;
+        const BTN_MIDDLE: u32 = 0x112;
+
+        if let Some(left_click) = self.pending.left_click {
+            let state_val = if left_click { wl_pointer::ButtonState::Pressed } else { wl_pointer::ButtonState::Released };
+            virtual_pointer.button(0, BTN_LEFT, state_val);
+            self.prev_left_click = left_click;
+        }
+
+        if let Some(right_click) = self.pending.right_click {
+            let state_val = if right_click { wl_pointer::ButtonState::Pressed } else { wl_pointer::ButtonState::Released };
+            virtual_pointer.button(0, BTN_RIGHT, state_val);
+            self.prev_right_click = right_click;
+        }
+
+        if let Some(middle_click) = self.pending.middle_click {
+            let state_val = if middle_click { wl_pointer::ButtonState::Pressed } else { wl_pointer::ButtonState::Released };
+            virtual_pointer.button(0, BTN_MIDDLE, state_val);
+            self.prev_middle_click = middle_click;
+        }
+
+        if self.pending.scroll != 0.0 {
+            virtual_pointer.axis(0, wl_pointer::Axis::VerticalScroll, self.pending.scroll);
+        }
+
+        virtual_pointer.frame();
+        self.pending = PendingPointer::default();
     }
 }
 
@@ -183,6 +389,34 @@ impl Dispatch<zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1, ()> for State {
     fn event(_: &mut Self, _: &zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1, _: zwlr_virtual_pointer_v1::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
 }
 
+impl Dispatch<wl_output::WlOutput, u32> for State {
+    fn event(
+        state: &mut Self,
+        _: &wl_output::WlOutput,
+        event: wl_output::Event,
+        name: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let output = state.outputs.entry(*name).or_default();
+        match event {
+            wl_output::Event::Geometry { x, y, .. } => {
+                output.x = x;
+                output.y = y;
+            }
+            wl_output::Event::Mode { flags, width, height, .. } => {
+                if let Ok(flags) = flags.into_result() {
+                    if flags.contains(wl_output::Mode::Current) {
+                        output.width = width;
+                        output.height = height;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 impl Dispatch<wl_registry::WlRegistry, ()> for State {
     fn event(
         state: &mut Self,
@@ -192,8 +426,8 @@ impl Dispatch<wl_registry::WlRegistry, ()> for State {
         _: &Connection,
         qh: &QueueHandle<Self>,
     ) {
-        if let wl_registry::Event::Global { name, interface, .. } = event {
-            match interface.as_str() {
+        match event {
+            wl_registry::Event::Global { name, interface, .. } => match interface.as_str() {
                 "zwlr_virtual_pointer_manager_v1" => {
                     state.pointer_manager = Some(registry.bind(
                         name,
@@ -202,8 +436,16 @@ impl Dispatch<wl_registry::WlRegistry, ()> for State {
                         (),
                     ));
                 }
+                "wl_output" => {
+                    registry.bind::<wl_output::WlOutput, _, _>(name, 2, qh, name);
+                    state.outputs.insert(name, Output::default());
+                }
                 _ => {},
+            },
+            wl_registry::Event::GlobalRemove { name } => {
+                state.outputs.remove(&name);
             }
+            _ => {}
         }
     }
 }
@@ -233,24 +475,77 @@ impl std::fmt::Display for WaylandError {
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Selected once at startup: `--relative` sends raw deltas instead of
+    // clamped absolute positions.
+    let pointer_mode = if std::env::args().any(|arg| arg == "--relative") {
+        PointerMode::Relative
+    } else {
+        PointerMode::Absolute
+    };
+
+    let bindings = config::load();
+    let tick = Duration::from_millis(bindings.tick_ms);
+
     // Set up Wayland connection
     let conn = Connection::connect_to_env()?;
     let display = conn.display();
     let mut event_queue = conn.new_event_queue();
     let qh = event_queue.handle();
 
-    let mut state = State::new();
+    let mut state = State::new(pointer_mode, bindings);
     display.get_registry(&qh, ());
+    // The first roundtrip only guarantees the `Global` events (including the
+    // `wl_output` binds triggered while handling them) have been sent; a
+    // second roundtrip is needed so the outputs' own `Geometry`/`Mode`
+    // events have actually arrived before we start computing screen bounds.
+    event_queue.roundtrip(&mut state)?;
     event_queue.roundtrip(&mut state)?;
 
     let mut input = Libinput::new_with_udev(InputHandler);
     input.udev_assign_seat("seat0").map_err(|_| WaylandError)?;
 
-    // Main loop
-    loop {
-        process_input_events(&mut input, &mut state);
-        state.update_and_handle_mouse_state();
-        event_queue.dispatch_pending(&mut state)?;
-        thread::sleep(Duration::from_millis(SLEEP_MS));
-    }
+    let mut event_loop: EventLoop<State> = EventLoop::try_new()?;
+    let handle = event_loop.handle();
+
+    WaylandSource::new(conn, event_queue)?.insert(handle.clone())?;
+
+    // Tracks whether the tick timer is currently armed, so the libinput
+    // callback doesn't insert a second one while movement keys are held.
+    let timer_armed = Rc::new(Cell::new(false));
+    let timer_armed_for_input = Rc::clone(&timer_armed);
+    let timer_handle = handle.clone();
+
+    handle.insert_source(
+        Generic::new(input, Interest::READ, Mode::Level),
+        move |_, input, state: &mut State| {
+            process_input_events(input, state);
+            // Handle clicks and the drag-lock toggle immediately, independent of the
+            // tick timer; movement is accumulated only from the timer below so the
+            // acceleration curve's held-ticks count stays tied to wall-clock time.
+            state.update_buttons();
+            state.flush();
+
+            if state.has_continuous_keys() && !timer_armed_for_input.get() {
+                timer_armed_for_input.set(true);
+                let timer_armed = Rc::clone(&timer_armed_for_input);
+                timer_handle
+                    .insert_source(Timer::from_duration(tick), move |_, _, state: &mut State| {
+                        state.tick_movement();
+                        state.flush();
+                        if state.has_continuous_keys() {
+                            TimeoutAction::ToDuration(tick)
+                        } else {
+                            timer_armed.set(false);
+                            TimeoutAction::Drop
+                        }
+                    })
+                    .expect("register tick timer");
+            }
+
+            Ok(PostAction::Continue)
+        },
+    )?;
+
+    event_loop.run(None, &mut state, |_| {})?;
+    Ok(())
 }